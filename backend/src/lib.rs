@@ -1,20 +1,25 @@
 use anyhow::{Context, Result};
 use log::debug;
-use std::collections::HashMap;
 use std::path::PathBuf;
-use types::{Currency, Transaction};
+use types::Transaction;
 
 // Reexport things callers might to use.
-pub use calculator::CalculatorType;
-pub use reader::ReaderType;
+pub use calculator::{CalculatorType, CapitalGainsResult, EntityType};
+pub use reader::{ApiCredentials, ExchangeType, ReaderType};
+pub use types::{Currency, Disposal, FinancialYear, FinancialYearGain};
 
 #[derive(Debug)]
 pub struct TransactionsFile {
     /// Path to the file.
     pub path: PathBuf,
 
-    /// What reader to use for the transactions file.
-    pub reader_type: ReaderType,
+    /// What reader to use for the transactions file. `None` means auto-detect it
+    /// from the file's extension/header, via `ReaderType::detect`.
+    pub reader_type: Option<ReaderType>,
+
+    /// Path to the sidecar reader config, required when `reader_type` is
+    /// `ReaderType::Generic`.
+    pub reader_config_path: Option<PathBuf>,
 }
 
 /// This function takes in a vec of TransactionsFiles, what readers to use for them,
@@ -22,7 +27,12 @@ pub struct TransactionsFile {
 fn read_transactions(transactions_files: Vec<TransactionsFile>) -> Result<Vec<Transaction>> {
     let mut transactions: Vec<Transaction> = Vec::new();
     for transactions_file in transactions_files {
-        let reader = transactions_file.reader_type.get_reader();
+        let reader_type = match transactions_file.reader_type {
+            Some(reader_type) => reader_type,
+            None => ReaderType::detect(&transactions_file.path)
+                .context("Failed to auto-detect a reader")?,
+        };
+        let reader = reader_type.get_reader(transactions_file.reader_config_path.as_ref())?;
         let mut ts = reader.read_transactions(&transactions_file.path)?;
         transactions.append(&mut ts);
     }
@@ -30,20 +40,52 @@ fn read_transactions(transactions_files: Vec<TransactionsFile>) -> Result<Vec<Tr
 }
 
 /// This function takes in a vec of Transactions and processes them depending on the
-/// chosen calcuator strategy.
+/// chosen calcuator strategy. Pass `include_disposals = true` to also get back the
+/// individual matched buy/sell pairs behind the totals (broken down by currency),
+/// e.g. to render a report.
 pub fn calculate_capital_gains(
     transactions_files: Vec<TransactionsFile>,
     calculator_type: CalculatorType,
-) -> Result<HashMap<Currency, f64>> {
+    entity_type: EntityType,
+    include_disposals: bool,
+) -> Result<CapitalGainsResult> {
     let transactions =
         read_transactions(transactions_files).context("Failed to read transactions")?;
     debug!("Transactions:");
     for t in &transactions {
         debug!("{:?}", t);
     }
-    let calcuator = calculator_type.get_calculator();
+    let calcuator = calculator_type.get_calculator(entity_type);
+    let capital_gains = calcuator
+        .calculate_capital_gains(transactions, include_disposals)
+        .context("Failed to calculate capital gains")?;
+    Ok(capital_gains)
+}
+
+/// Like `calculate_capital_gains`, but fetches transaction history live from an
+/// exchange's API instead of reading it from CSV exports on disk. This lets the
+/// CLI take an exchange name and a credentials file rather than `--paths`.
+pub async fn calculate_capital_gains_from_api(
+    exchange_type: ExchangeType,
+    credentials_path: PathBuf,
+    calculator_type: CalculatorType,
+    entity_type: EntityType,
+    include_disposals: bool,
+) -> Result<CapitalGainsResult> {
+    let credentials = ApiCredentials::load(&credentials_path)
+        .context("Failed to load exchange API credentials")?;
+    let transactions = exchange_type
+        .get_api_reader()?
+        .fetch_transactions(&credentials)
+        .await
+        .context("Failed to fetch transactions from the exchange API")?;
+    debug!("Transactions:");
+    for t in &transactions {
+        debug!("{:?}", t);
+    }
+    let calcuator = calculator_type.get_calculator(entity_type);
     let capital_gains = calcuator
-        .calculate_capital_gains(transactions)
+        .calculate_capital_gains(transactions, include_disposals)
         .context("Failed to calculate capital gains")?;
     Ok(capital_gains)
 }
@@ -1,53 +1,127 @@
+use crate::lot_store::{LotStore, MemLotStore};
+use crate::traits::CapitalGainsResult;
 use crate::Calculator;
 use anyhow::{bail, Result};
 use log::debug;
-use std::collections::{HashMap, VecDeque};
-use types::{Currency, Transaction, TransactionType};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use types::{Currency, Disposal, FinancialYear, FinancialYearGain, Transaction, TransactionType};
 
-pub struct FifoCalculator {}
+pub struct FifoCalculator {
+    // The Australian CGT discount rate to apply to eligible gains, e.g. 50% for
+    // individuals/trusts or 33⅓% for super funds. See `EntityType::discount_rate`.
+    discount_rate: Decimal,
+
+    // Where carried-forward open lots and running realized gains live. Behind a
+    // `RefCell` since `Calculator::calculate_capital_gains` only takes `&self`, but
+    // processing a run needs to load from and write back to the store.
+    lot_store: RefCell<Box<dyn LotStore>>,
+}
+
+impl FifoCalculator {
+    /// Builds a `FifoCalculator` backed by a fresh, process-local `MemLotStore`, i.e.
+    /// every run starts with no carried-forward lots.
+    pub fn new(discount_rate: Decimal) -> FifoCalculator {
+        FifoCalculator::with_lot_store(discount_rate, Box::new(MemLotStore::new()))
+    }
+
+    /// Builds a `FifoCalculator` backed by the given `LotStore`, so open lots and
+    /// realized gains can be carried forward across separate runs, e.g. processing
+    /// one financial year's transactions at a time.
+    pub fn with_lot_store(discount_rate: Decimal, lot_store: Box<dyn LotStore>) -> FifoCalculator {
+        FifoCalculator {
+            discount_rate,
+            lot_store: RefCell::new(lot_store),
+        }
+    }
+
+    /// The realized capital gain recorded for `currency` across every run processed
+    /// by this calculator's `LotStore` so far, including the current run once it has
+    /// completed.
+    pub fn realized_gain(&self, currency: &Currency) -> Decimal {
+        self.lot_store.borrow().realized_gain(currency)
+    }
+}
 
 impl Calculator for FifoCalculator {
     fn calculate_capital_gains(
         &self,
         mut transactions: Vec<Transaction>,
-    ) -> Result<HashMap<Currency, f64>> {
-        // Sort transactions by unixtime.
+        include_disposals: bool,
+    ) -> Result<CapitalGainsResult> {
+        // Sort transactions by unixtime up front, since both the per-currency grouping
+        // below and FIFO matching within a currency depend on chronological order.
         transactions.sort_by(|a, b| a.unixtime.partial_cmp(&b.unixtime).unwrap());
 
-        // Figure out all the currencies we're working with.
-        let currencies: Vec<Currency> = transactions.iter().map(|t| t.currency.clone()).collect();
+        // Drain transactions into per-currency buckets in a single pass, moving each
+        // transaction rather than cloning it, instead of rescanning the whole list
+        // once per currency.
+        let mut by_currency: HashMap<Currency, Vec<Transaction>> = HashMap::new();
+        for transaction in transactions.into_iter() {
+            by_currency
+                .entry(transaction.currency.clone())
+                .or_insert_with(Vec::new)
+                .push(transaction);
+        }
 
-        // Get the capital gain for each currency.
-        let mut capital_gains = HashMap::new();
-        for currency in currencies.into_iter() {
+        // FIFO lot matching happens per currency, since a sell can only be matched
+        // against buys of the same asset. Loss netting below does not: it pools
+        // every currency's disposals together, per ATO rules.
+        let mut all_disposals: HashMap<Currency, Vec<Disposal>> = HashMap::new();
+        for (currency, currency_transactions) in by_currency.into_iter() {
             debug!("Determining capital gain for {}", currency.0);
-            let mut currency_transactions: Vec<Transaction> = Vec::new();
-            for t in transactions.iter() {
-                if t.currency == currency {
-                    currency_transactions.push(t.clone());
-                }
-            }
-            let capital_gain = self.calculate_capital_gains_single_currency(currency_transactions)?;
-            capital_gains.insert(currency, capital_gain);
+            let disposals =
+                self.calculate_capital_gains_single_currency(&currency, currency_transactions)?;
+            all_disposals.insert(currency, disposals);
         }
 
-        Ok(capital_gains)
+        let flattened_disposals: Vec<&Disposal> = all_disposals.values().flatten().collect();
+        let totals = self.net_by_financial_year(&flattened_disposals);
+
+        Ok(CapitalGainsResult {
+            totals,
+            disposals: include_disposals.then(|| all_disposals),
+        })
     }
 }
 
 impl FifoCalculator {
     // This function does not assert that all the transactions are indeed
     // for a single currency, but if they're not, it'll fail down the line.
-    fn calculate_capital_gains_single_currency(&self, transactions: Vec<Transaction>) -> Result<f64> {
-        // We keep track of purchases as individual lots in a queue (FIFO).
-        let mut lots: VecDeque<Transaction> = VecDeque::new();
+    //
+    // Returns the full per-lot disposal ledger (acquisition date, cost base,
+    // proceeds and gain/loss for every matched buy/sell pair), not just the
+    // summed capital gain, so callers can print or serialize a line-by-line
+    // breakdown rather than only a bare total.
+    fn calculate_capital_gains_single_currency(
+        &self,
+        currency: &Currency,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<Disposal>> {
+        // We keep track of purchases as individual lots in a queue (FIFO), seeded
+        // with whatever open lots were carried forward from an earlier run.
+        let mut lots: VecDeque<Transaction> =
+            self.lot_store.borrow().load_lots(currency).into_iter().collect();
 
-        // Track the ultimate capital gain.
-        let mut capital_gain = 0.0;
+        // Every buy id we've ever queued, so a `Reversal` naming an id that's no
+        // longer in `lots` can be told apart (already fully consumed) from one that
+        // never existed (a data error). Seeded from the carried-forward lots too.
+        let mut all_buy_ids: HashSet<String> = lots.iter().map(|lot| lot.id.clone()).collect();
+
+        // Track every matched buy/sell pair we encounter.
+        let mut disposals: Vec<Disposal> = Vec::new();
 
         for transaction in transactions.into_iter() {
+            // Grabbed up front since matching on `transaction.transaction_type`
+            // below partially moves it out of `transaction`.
+            let transaction_id = transaction.id.clone();
             match transaction.transaction_type {
-                TransactionType::Buy => lots.push_back(transaction),
+                TransactionType::Buy => {
+                    all_buy_ids.insert(transaction_id);
+                    lots.push_back(transaction);
+                }
                 TransactionType::Sell => {
                     // While this transaction has value remaining, use it to
                     // to subtract value from the lot at the head of the queue.
@@ -61,19 +135,205 @@ impl FifoCalculator {
                             // missing sell events.
                             bail!("There is a sell Transaction but no buy remaining to subtract it from {:?}. This means the data is incomplete, and specifically is missing buy events", sell);
                         }
-                        let capital_gain_delta = lots[0].subtract_sell(&mut sell);
+                        let disposal = lots[0].subtract_sell(&mut sell);
                         if lots[0].is_exhausted() {
                             // There is nothing left in this buy, pop it and move to
                             // next one to continue to deplete this sell.
                             lots.pop_front();
                         }
-                        capital_gain += capital_gain_delta;
+                        disposals.push(disposal);
                     }
                     // There is nothing left in this sell, move on to the next transaction.
                 }
+                TransactionType::Reversal(reversed_id) => {
+                    // We only find out whether the reversed buy has already started
+                    // being consumed by replaying events in chronological order, so
+                    // this check has to live here rather than in a pass that runs
+                    // before FIFO matching starts.
+                    match lots.iter().position(|lot| lot.id == reversed_id) {
+                        Some(index) => {
+                            if !lots[index].is_untouched() {
+                                bail!(
+                                    "Reversal transaction {} can't be applied: buy transaction {} has already been partially consumed by an earlier sell",
+                                    transaction_id, reversed_id
+                                );
+                            }
+                            lots.remove(index);
+                        }
+                        None => {
+                            if all_buy_ids.contains(&reversed_id) {
+                                bail!(
+                                    "Reversal transaction {} can't be applied: buy transaction {} has already been fully consumed by an earlier sell",
+                                    transaction_id, reversed_id
+                                );
+                            }
+                            bail!(
+                                "Reversal transaction {} references unknown buy transaction {}",
+                                transaction_id, reversed_id
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // Write back whatever remains unexhausted so the next run against this
+        // currency can carry it forward, and add this run's realized gain to the
+        // running total.
+        self.lot_store
+            .borrow_mut()
+            .save_lots(currency, lots.into_iter().collect());
+        let realized_gain = disposals
+            .iter()
+            .fold(dec!(0), |total, disposal| total + disposal.gain);
+        self.lot_store
+            .borrow_mut()
+            .add_realized_gain(currency, realized_gain);
+
+        Ok(disposals)
+    }
+
+    /// Group disposals from every currency by the financial year of their sell
+    /// date and net them down to a single assessable capital gain per year,
+    /// applying the ATO's rules for the 12 month discount.
+    ///
+    /// The ATO pools capital gains and losses across all of a taxpayer's CGT
+    /// assets within a financial year rather than siloing them per asset, so a
+    /// loss on one currency offsets a gain on another in the same year. Per
+    /// financial year: capital losses are netted against non-discounted gains
+    /// first (to preserve as much of the discountable gain as possible), any losses
+    /// remaining after that are netted against discounted gains, and only then is the
+    /// discount rate applied to whatever discount-eligible gain survives. A net
+    /// capital loss that exceeds a year's gains doesn't just vanish: it carries
+    /// forward and is applied against the next financial year (in this run, and
+    /// across runs via `LotStore`), since capital losses never expire under ATO rules.
+    fn net_by_financial_year(
+        &self,
+        disposals: &[&Disposal],
+    ) -> HashMap<FinancialYear, FinancialYearGain> {
+        let mut by_year: HashMap<FinancialYear, Vec<&Disposal>> = HashMap::new();
+        for disposal in disposals {
+            by_year
+                .entry(disposal.financial_year())
+                .or_insert_with(Vec::new)
+                .push(disposal);
+        }
+
+        // Losses carry forward to *later* years, so years must be netted in
+        // chronological order, carrying forward the loss left over from the
+        // previous one. `FinancialYear`'s `Ord` is chronological.
+        let mut years: Vec<FinancialYear> = by_year.keys().copied().collect();
+        years.sort();
+
+        let mut carried_loss = self.lot_store.borrow().carried_loss();
+        let mut result = HashMap::new();
+        for year in years {
+            let year_disposals = &by_year[&year];
+            let (gain, leftover_loss) = self.net_financial_year(year_disposals, carried_loss);
+            carried_loss = leftover_loss;
+            result.insert(year, gain);
+        }
+        self.lot_store.borrow_mut().set_carried_loss(carried_loss);
+
+        result
+    }
+
+    /// Nets one financial year's disposals down to a `FinancialYearGain`, folding in
+    /// `carried_loss` brought forward from an earlier year. Returns the gain for this
+    /// year alongside whatever loss is left over to carry into the next one.
+    fn net_financial_year(
+        &self,
+        disposals: &[&Disposal],
+        carried_loss: Decimal,
+    ) -> (FinancialYearGain, Decimal) {
+        let mut discounted_gains = dec!(0);
+        let mut non_discounted_gains = dec!(0);
+        let mut losses = carried_loss;
+
+        for disposal in disposals {
+            if disposal.gain < dec!(0) {
+                losses += -disposal.gain;
+            } else if disposal.discount_eligible {
+                discounted_gains += disposal.gain;
+            } else {
+                non_discounted_gains += disposal.gain;
             }
         }
 
-        Ok(capital_gain)
+        // Reduce non-discounted gains first to maximize the amount left to discount.
+        let non_discounted_net = (non_discounted_gains - losses).max(dec!(0));
+        let leftover_losses = (losses - non_discounted_gains).max(dec!(0));
+        let discounted_net = (discounted_gains - leftover_losses).max(dec!(0));
+        let losses_carried_forward = (leftover_losses - discounted_gains).max(dec!(0));
+
+        // The discount rate is the share of the gain that is forgiven, so what's
+        // left assessable is the rest of it.
+        let net_gain = non_discounted_net + (discounted_net * (dec!(1) - self.discount_rate));
+
+        let gain = FinancialYearGain {
+            discounted_gains,
+            non_discounted_gains,
+            losses,
+            net_gain,
+            losses_carried_forward,
+        };
+        (gain, losses_carried_forward)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disposal(gain: Decimal, discount_eligible: bool) -> Disposal {
+        Disposal {
+            buy_unixtime: 0,
+            sell_unixtime: 0,
+            quantity: dec!(1),
+            cost_base_aud: dec!(0),
+            proceeds_aud: gain,
+            gain,
+            discount_eligible,
+        }
+    }
+
+    #[test]
+    fn net_financial_year_nets_losses_against_non_discounted_gains_first() {
+        let calculator = FifoCalculator::new(dec!(0.5));
+        let disposals = vec![
+            disposal(dec!(100), false),
+            disposal(dec!(100), true),
+            disposal(dec!(-150), false),
+        ];
+        let disposal_refs: Vec<&Disposal> = disposals.iter().collect();
+        let (gain, leftover_loss) = calculator.net_financial_year(&disposal_refs, dec!(0));
+        // The $150 loss fully absorbs the $100 non-discounted gain, then the
+        // remaining $50 eats into the discounted gain, leaving $50 to discount.
+        assert_eq!(gain.non_discounted_gains, dec!(100));
+        assert_eq!(gain.discounted_gains, dec!(100));
+        assert_eq!(gain.losses, dec!(150));
+        assert_eq!(gain.net_gain, dec!(25));
+        assert_eq!(gain.losses_carried_forward, dec!(0));
+        assert_eq!(leftover_loss, dec!(0));
+    }
+
+    #[test]
+    fn net_financial_year_applies_the_discount_rate_only_to_the_discounted_bucket() {
+        let calculator = FifoCalculator::new(dec!(0.5));
+        let disposals = vec![disposal(dec!(100), false), disposal(dec!(100), true)];
+        let disposal_refs: Vec<&Disposal> = disposals.iter().collect();
+        let (gain, _) = calculator.net_financial_year(&disposal_refs, dec!(0));
+        assert_eq!(gain.net_gain, dec!(150));
+    }
+
+    #[test]
+    fn net_financial_year_carries_forward_a_loss_that_exceeds_the_years_gains() {
+        let calculator = FifoCalculator::new(dec!(0.5));
+        let disposals = vec![disposal(dec!(10), false), disposal(dec!(10), true)];
+        let disposal_refs: Vec<&Disposal> = disposals.iter().collect();
+        let (gain, leftover_loss) = calculator.net_financial_year(&disposal_refs, dec!(50));
+        assert_eq!(gain.net_gain, dec!(0));
+        assert_eq!(gain.losses_carried_forward, dec!(30));
+        assert_eq!(leftover_loss, dec!(30));
     }
 }
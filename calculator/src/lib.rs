@@ -1,23 +1,52 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use structopt::clap::arg_enum;
 
 mod fifo;
+mod lot_store;
 mod traits;
 
 use crate::fifo::FifoCalculator;
 use crate::traits::Calculator;
 
+pub use crate::lot_store::{LotStore, MemLotStore};
+pub use crate::traits::CapitalGainsResult;
+
 arg_enum! {
 /// This enum registers all the different calculator options.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum CalculatorType {
     Fifo,
 }
 }
 
 impl CalculatorType {
-    pub fn get_calculator(&self) -> Box<dyn Calculator> {
+    pub fn get_calculator(&self, entity_type: EntityType) -> Box<dyn Calculator> {
+        match &self {
+            Self::Fifo => Box::new(FifoCalculator::new(entity_type.discount_rate())),
+        }
+    }
+}
+
+arg_enum! {
+/// The kind of taxpayer the CGT discount is being calculated for. The Australian
+/// 12 month CGT discount rate depends on this: individuals and trusts get 50%,
+/// while complying super funds only get 33⅓% (one third).
+#[derive(Clone, Debug)]
+pub enum EntityType {
+    Individual,
+    SuperFund,
+}
+}
+
+impl EntityType {
+    pub fn discount_rate(&self) -> Decimal {
         match &self {
-            Self::Fifo => Box::new(FifoCalculator {}),
+            Self::Individual => dec!(0.5),
+            // One third to `Decimal`'s full precision, not the rounded `0.333`, since that would
+            // understate the discount (and overstate assessable gain) on every
+            // super fund return.
+            Self::SuperFund => dec!(1) / dec!(3),
         }
     }
 }
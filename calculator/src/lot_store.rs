@@ -0,0 +1,84 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use types::{Currency, Transaction};
+
+/// Persists the state `FifoCalculator` needs to carry across separate runs: the
+/// open buy lots left over for each currency, and the realized gain recorded for
+/// it so far. This is what lets a caller feed in FY2023 transactions, snapshot
+/// state, then feed in FY2024 transactions and get a correct continuation without
+/// re-supplying FY2023's history.
+///
+/// `MemLotStore` is the only implementation today, so state doesn't actually
+/// survive past the end of the process; a durable implementation (file- or
+/// database-backed) can implement this same trait without `FifoCalculator`
+/// changing at all.
+pub trait LotStore {
+    /// Open buy lots carried forward for `currency`, oldest first. Returns an
+    /// empty `Vec` the first time a currency is seen.
+    fn load_lots(&self, currency: &Currency) -> Vec<Transaction>;
+
+    /// Replace the carried-forward lots for `currency` with whatever remains
+    /// unexhausted at the end of a run.
+    fn save_lots(&mut self, currency: &Currency, lots: Vec<Transaction>);
+
+    /// The running total of realized capital gain recorded for `currency` across
+    /// every run so far, before the current run's disposals are added.
+    fn realized_gain(&self, currency: &Currency) -> Decimal;
+
+    /// Adds `gain` to the running realized-gain total for `currency`.
+    fn add_realized_gain(&mut self, currency: &Currency, gain: Decimal);
+
+    /// The net capital loss carried forward from earlier financial years that
+    /// hasn't yet been applied against a gain. Returns zero before any run has
+    /// recorded one. Pooled across every currency rather than kept per-currency,
+    /// since the ATO nets capital gains and losses across all of a taxpayer's CGT
+    /// assets within a financial year, not asset by asset. Capital losses never
+    /// expire under ATO rules, so this is how a loss-making year's excess loss
+    /// survives to offset a later run's gains instead of being dropped.
+    fn carried_loss(&self) -> Decimal;
+
+    /// Replaces the carried-forward loss with whatever remains unapplied at the
+    /// end of a run.
+    fn set_carried_loss(&mut self, loss: Decimal);
+}
+
+/// The default, in-memory `LotStore`. State lives only as long as the process.
+#[derive(Default)]
+pub struct MemLotStore {
+    lots: HashMap<Currency, Vec<Transaction>>,
+    realized_gains: HashMap<Currency, Decimal>,
+    carried_loss: Decimal,
+}
+
+impl MemLotStore {
+    pub fn new() -> MemLotStore {
+        MemLotStore::default()
+    }
+}
+
+impl LotStore for MemLotStore {
+    fn load_lots(&self, currency: &Currency) -> Vec<Transaction> {
+        self.lots.get(currency).cloned().unwrap_or_default()
+    }
+
+    fn save_lots(&mut self, currency: &Currency, lots: Vec<Transaction>) {
+        self.lots.insert(currency.clone(), lots);
+    }
+
+    fn realized_gain(&self, currency: &Currency) -> Decimal {
+        self.realized_gains.get(currency).copied().unwrap_or(dec!(0))
+    }
+
+    fn add_realized_gain(&mut self, currency: &Currency, gain: Decimal) {
+        *self.realized_gains.entry(currency.clone()).or_insert(dec!(0)) += gain;
+    }
+
+    fn carried_loss(&self) -> Decimal {
+        self.carried_loss
+    }
+
+    fn set_carried_loss(&mut self, loss: Decimal) {
+        self.carried_loss = loss;
+    }
+}
@@ -1,11 +1,30 @@
 use anyhow::Result;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use types::{Currency, Transaction};
+use types::{Currency, Disposal, FinancialYear, FinancialYearGain, Transaction};
+
+/// The outcome of running a `Calculator` over a set of transactions.
+pub struct CapitalGainsResult {
+    /// The net assessable capital gain for each Australian financial year (1 July
+    /// - 30 June), pooled across every currency the way the ATO requires (a loss
+    /// on one asset offsets a gain on another within the same year), along with
+    /// the gross buckets (discounted gains, non-discounted gains, losses) it was
+    /// netted down from.
+    pub totals: HashMap<FinancialYear, FinancialYearGain>,
+
+    /// The individual matched buy/sell pairs behind `totals`, present when the
+    /// caller asked for them. This is the audit trail needed to substantiate a
+    /// return to the ATO.
+    pub disposals: Option<HashMap<Currency, Vec<Disposal>>>,
+}
 
 pub trait Calculator {
+    /// Computes capital gains for the given transactions. When `include_disposals`
+    /// is true, `CapitalGainsResult::disposals` is populated with every matched
+    /// buy/sell pair; otherwise it is left as `None` to avoid the caller having to
+    /// pay for data it won't use.
     fn calculate_capital_gains(
         &self,
         transactions: Vec<Transaction>,
-    ) -> Result<HashMap<Currency, f64>>;
+        include_disposals: bool,
+    ) -> Result<CapitalGainsResult>;
 }
@@ -1,7 +1,12 @@
 use anyhow::{bail, Result};
-use backend::{calculate_capital_gains, CalculatorType, ReaderType, TransactionsFile};
+use backend::{
+    calculate_capital_gains, calculate_capital_gains_from_api, CalculatorType, CapitalGainsResult,
+    EntityType, ExchangeType, ReaderType, TransactionsFile,
+};
 use log::info;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
 use structopt::clap::AppSettings::ColoredHelp;
 
@@ -16,21 +21,55 @@ struct Args {
     #[structopt(short, long)]
     debug: bool,
 
-    /// Paths to files containing transactions.
+    /// Paths to files containing transactions. Mutually exclusive with --exchange.
     #[structopt(short, long)]
     paths: Vec<PathBuf>,
 
-    /// Readers you want to use for these files.
-    /// The order here must match the order of the given paths.
-    #[structopt(short, long, required = true, possible_values = &ReaderType::variants(), case_insensitive = true)]
-    readers: Vec<ReaderType>,
+    /// Readers you want to use for these files, one per path in the same order as
+    /// --paths. Pass an empty string for a path you want auto-detected from its
+    /// extension/header instead of naming a reader explicitly.
+    #[structopt(short, long)]
+    readers: Vec<String>,
+
+    /// Sidecar config files for the `Generic` reader, one per path, in the same
+    /// order as `--paths`. Pass an empty string for paths not using `Generic`.
+    #[structopt(long)]
+    reader_configs: Vec<String>,
+
+    /// Experimental, not yet usable: fetch transaction history live from an
+    /// exchange's API instead of reading CSV exports from --paths. No `ExchangeType`
+    /// is wired to a real exchange yet (see `ExchangeType::get_api_reader`), so
+    /// every value currently just fails with a clear error. Requires --credentials.
+    #[structopt(long, possible_values = &ExchangeType::variants(), case_insensitive = true, hidden = true)]
+    exchange: Option<ExchangeType>,
+
+    /// Path to a JSON file with the API key/secret for --exchange. See --exchange:
+    /// this flag is not yet usable with any real exchange.
+    #[structopt(long, hidden = true)]
+    credentials: Option<PathBuf>,
 
     /// Strategy you want to use for calculating the capital gains.
     #[structopt(short, long, required = true, possible_values = &CalculatorType::variants(), case_insensitive = true)]
     calculator: CalculatorType,
+
+    /// Kind of taxpayer to calculate the CGT discount for: individuals and trusts
+    /// get a 50% discount, complying super funds only get 33⅓%.
+    #[structopt(long, possible_values = &EntityType::variants(), case_insensitive = true, default_value = "Individual")]
+    entity_type: EntityType,
+
+    /// Print a detailed table of every matched buy/sell disposal, not just the
+    /// per-financial-year totals.
+    #[structopt(long)]
+    report: bool,
+
+    /// Directory to write one CSV report per currency into. Implies `--report`'s
+    /// data collection, but does not also print to stdout unless `--report` is set.
+    #[structopt(long)]
+    report_csv_dir: Option<PathBuf>,
 }
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     let args = Args::from_args();
 
     if args.debug {
@@ -39,23 +78,106 @@ fn main() -> Result<()> {
         env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     }
 
-    if args.paths.len() != args.readers.len() {
-        bail!("Please pass 1 reader per path");
+    let include_disposals = args.report || args.report_csv_dir.is_some();
+
+    let capital_gains = match (&args.exchange, &args.credentials) {
+        (Some(exchange), Some(credentials)) => {
+            if !args.paths.is_empty() {
+                bail!("Please pass either --paths or --exchange/--credentials, not both");
+            }
+            calculate_capital_gains_from_api(
+                exchange.clone(),
+                credentials.clone(),
+                args.calculator.clone(),
+                args.entity_type.clone(),
+                include_disposals,
+            )
+            .await?
+        }
+        (None, None) => {
+            if !args.readers.is_empty() && args.readers.len() != args.paths.len() {
+                bail!("Please pass 1 --readers entry per path (use an empty string for paths you want auto-detected)");
+            }
+            if !args.reader_configs.is_empty() && args.reader_configs.len() != args.paths.len() {
+                bail!("Please pass 1 --reader-configs entry per path (use an empty string for paths that don't need one)");
+            }
+            let mut transactions_files = Vec::new();
+            for (i, path) in args.paths.iter().enumerate() {
+                let reader_type = match args.readers.get(i).filter(|s| !s.is_empty()) {
+                    Some(reader) => Some(ReaderType::from_str(reader).map_err(|e| anyhow::anyhow!(e))?),
+                    None => None,
+                };
+                let reader_config_path = args
+                    .reader_configs
+                    .get(i)
+                    .filter(|s| !s.is_empty())
+                    .map(PathBuf::from);
+                let transactions_file = TransactionsFile {
+                    path: path.clone(),
+                    reader_type,
+                    reader_config_path,
+                };
+                transactions_files.push(transactions_file);
+            }
+            calculate_capital_gains(
+                transactions_files,
+                args.calculator.clone(),
+                args.entity_type.clone(),
+                include_disposals,
+            )?
+        }
+        _ => bail!("Please pass both --exchange and --credentials to fetch from an exchange API"),
+    };
+
+    print_and_report(capital_gains, &args);
+
+    Ok(())
+}
+
+fn print_and_report(capital_gains: CapitalGainsResult, args: &Args) {
+    let mut totals: Vec<_> = capital_gains.totals.into_iter().collect();
+    totals.sort_by(|x, y| x.0.cmp(&y.0));
+    for (financial_year, gain) in &totals {
+        info!(
+            "Capital gain in {}: ${:.2} AUD (discounted gains ${:.2}, non-discounted gains ${:.2}, losses ${:.2}, losses carried forward ${:.2})",
+            financial_year,
+            gain.net_gain,
+            gain.discounted_gains,
+            gain.non_discounted_gains,
+            gain.losses,
+            gain.losses_carried_forward,
+        );
     }
-    let mut transactions_files = Vec::new();
-    for (i, path) in args.paths.iter().enumerate() {
-        let reader = &args.readers[i];
-        let transactions_file = TransactionsFile {
-            path: path.clone(),
-            reader_type: reader.clone(),
-        };
-        transactions_files.push(transactions_file);
+
+    if let Some(disposals) = capital_gains.disposals {
+        if args.report {
+            for (currency, disposal_list) in &disposals {
+                let by_year = group_by_financial_year(disposal_list);
+                reporting::print_disposals(currency, &by_year);
+            }
+        }
+        if let Some(dir) = &args.report_csv_dir {
+            let _ = std::fs::create_dir_all(dir);
+            for (currency, disposal_list) in &disposals {
+                let by_year = group_by_financial_year(disposal_list);
+                let path = dir.join(format!("{}.csv", currency.0));
+                if let Err(e) = reporting::write_disposals_csv(&path, currency, &by_year) {
+                    log::error!("Failed to write report for {}: {:#}", currency.0, e);
+                }
+            }
+        }
     }
-    let capital_gains = calculate_capital_gains(transactions_files, args.calculator)?;
-    let mut capital_gains: Vec<_> = capital_gains.into_iter().collect();
-    capital_gains.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
-    for (currency, capital_gain) in capital_gains {
-        info!("Capital gain for {}: ${:.2} AUD", currency.0, capital_gain);
+}
+
+fn group_by_financial_year(
+    disposals: &[backend::Disposal],
+) -> HashMap<backend::FinancialYear, Vec<backend::Disposal>> {
+    let mut by_year: HashMap<backend::FinancialYear, Vec<backend::Disposal>> = HashMap::new();
+    for disposal in disposals {
+        by_year
+            .entry(disposal.financial_year())
+            .or_insert_with(Vec::new)
+            .push(disposal.clone());
     }
-    Ok(())
+    by_year
 }
@@ -1,5 +1,9 @@
 use anyhow::{Context, Result};
-use backend::{calculate_capital_gains, CalculatorType, Currency, ReaderType, TransactionsFile};
+use backend::{
+    calculate_capital_gains, calculate_capital_gains_from_api, CalculatorType, EntityType,
+    ExchangeType, ReaderType, TransactionsFile,
+};
+use backend::FinancialYear;
 use druid::commands::OPEN_FILE;
 use druid::im::{HashMap as ImHashMap, Vector};
 use druid::widget::{
@@ -14,6 +18,8 @@ use druid::{
 use druid_widget_nursery::dropdown::DROPDOWN_SHOW;
 use druid_widget_nursery::Dropdown;
 use itertools::izip;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -27,13 +33,18 @@ const WINDOW_TITLE: LocalizedString<InitialState> = LocalizedString::new(TITLE);
 struct InitialState {
     data_sources: Vector<DataPickerState>,
     calculator_type: String,
+    // Exchange to fetch transaction history from live, as an alternative to
+    // adding file-based data sources above.
+    exchange_type: String,
+    #[data(same_fn = "PartialEq::eq")]
+    credentials_path: Option<PathBuf>,
     // Only one of these two should be Some at the same time.
     // I would represent this differently, like as this:
-    // Option<Result<HashMap<Currency, f64>>>
+    // Option<Result<HashMap<FinancialYear, Decimal>>>
     // But the Data trait mandates that everything be cloneable and
     // anyhow::Error is not (and I couldn't get it to work properly
     // at runtime with an Arc).
-    capital_gains: Option<ImHashMap<Currency, f64>>,
+    capital_gains: Option<ImHashMap<FinancialYear, f64>>,
     error_text: Option<String>,
 }
 
@@ -42,6 +53,8 @@ impl InitialState {
         InitialState {
             data_sources: Vector::new(),
             calculator_type: CalculatorType::Fifo.to_string(),
+            exchange_type: ExchangeType::Generic.to_string(),
+            credentials_path: None,
             capital_gains: None,
             error_text: None,
         }
@@ -60,8 +73,15 @@ impl AppDelegate<InitialState> for FileOpenerDelegate {
         _env: &Env,
     ) -> Handled {
         if let Some(file_info) = cmd.get(OPEN_FILE) {
-            let data_picker_state = DataPickerState::new(file_info.path().to_path_buf());
-            data.data_sources.push_back(data_picker_state);
+            let path = file_info.path().to_path_buf();
+            // The credentials file dialog only allows picking `.json` files, and
+            // data sources never are, so the extension is enough to tell the two
+            // open dialogs apart here.
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                data.credentials_path = Some(path);
+            } else {
+                data.data_sources.push_back(DataPickerState::new(path));
+            }
             Handled::Yes
         } else {
             Handled::No
@@ -166,7 +186,7 @@ fn build_root_widget() -> impl Widget<InitialState> {
         1.0,
     );
 
-    // The button to calculate the capital gains.
+    // The button to calculate the capital gains from the data sources above.
     let calculate_button = Button::new("Calculate capital gains")
         .on_click(move |_, data: &mut InitialState, _| {
             // Just calculate in line, it's quick.
@@ -179,6 +199,78 @@ fn build_root_widget() -> impl Widget<InitialState> {
         .with_spacer(VERTICAL_WIDGET_SPACING)
         .with_child(calculate_button);
 
+    // As an alternative to adding file-based data sources, let the user fetch
+    // transaction history live from an exchange's API. No real exchange is wired
+    // up yet (see `ExchangeType::get_api_reader`), so this section is shown but
+    // left permanently disabled rather than offering a button that can only ever
+    // fail.
+    layout = layout.with_spacer(VERTICAL_WIDGET_SPACING).with_child(Label::new(
+        "Or, fetch transaction history from an exchange (experimental, not yet available):",
+    ));
+
+    let exchange_dropdown = Dropdown::new(
+        Button::new(|exchange_type_string: &String, _: &Env| exchange_type_string.to_string())
+            .on_click(|ctx: &mut EventCtx, _, _| ctx.submit_notification(DROPDOWN_SHOW)),
+        |_, _| {
+            let choices: Vec<(&str, String)> = izip!(
+                ExchangeType::variants(),
+                ExchangeType::variants()
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>()
+            )
+            .collect();
+            RadioGroup::new(choices)
+        },
+    )
+    .align_left()
+    .lens(InitialState::exchange_type);
+
+    let credentials_dialog_options = FileDialogOptions::new()
+        .allowed_types(vec![FileSpec::new("Credentials file", &["json"])])
+        .name_label("Select")
+        .title("Select exchange API credentials")
+        .button_text("Open");
+
+    let credentials_button = Button::new("Add credentials file").on_click(move |ctx, _, _| {
+        ctx.submit_command(druid::commands::SHOW_OPEN_PANEL.with(credentials_dialog_options.clone()))
+    });
+
+    let credentials_label = Label::new(|data: &InitialState, _env: &Env| match &data.credentials_path {
+        Some(path) => format!(
+            "Credentials: {}",
+            path.file_name().unwrap().to_string_lossy()
+        ),
+        None => "No credentials file selected".to_string(),
+    });
+
+    layout = layout
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Exchange:"))
+                .with_spacer(HORIZONTAL_WIDGET_SPACING)
+                .with_child(exchange_dropdown)
+                .with_spacer(HORIZONTAL_WIDGET_SPACING * 2.0)
+                .with_child(credentials_button)
+                .with_spacer(HORIZONTAL_WIDGET_SPACING)
+                .with_child(credentials_label),
+        )
+        .with_spacer(VERTICAL_WIDGET_SPACING);
+
+    // Always disabled: every `ExchangeType` currently bails out of
+    // `get_api_reader`, so there is no credentials file that would make this
+    // button succeed.
+    let calculate_from_exchange_button = Button::new("Calculate from exchange")
+        .on_click(move |_, data: &mut InitialState, _| {
+            let (capital_gains, error_text) = calculate_capital_gains_from_exchange_local(&data);
+            data.capital_gains = capital_gains;
+            data.error_text = error_text;
+        })
+        .disabled_if(|_, _| true);
+    layout = layout
+        .with_child(calculate_from_exchange_button)
+        .with_spacer(VERTICAL_WIDGET_SPACING);
+
     // If we have results, present those. I think we have to use a List for this.
     // Fortunately the results are in a list format anyway so this works.
     let results_widget = SizedBox::new(ViewSwitcher::new(
@@ -186,10 +278,10 @@ fn build_root_widget() -> impl Widget<InitialState> {
         move |_, data: &InitialState, _env| {
             if data.capital_gains.is_some() {
                 let mut column = Flex::column();
-                for (currency, capital_gain) in data.capital_gains.as_ref().unwrap() {
+                for (financial_year, capital_gain) in data.capital_gains.as_ref().unwrap() {
                     column.add_child(Label::new(format!(
-                        "Capital gain for {}: ${:.2} AUD",
-                        currency.0, capital_gain
+                        "Capital gain in {}: ${:.2} AUD",
+                        financial_year, capital_gain
                     )));
                 }
                 Box::new(column)
@@ -216,20 +308,74 @@ fn build_root_widget() -> impl Widget<InitialState> {
 
 fn calculate_capital_gains_local(
     data: &InitialState,
-) -> (Option<ImHashMap<Currency, f64>>, Option<String>) {
+) -> (Option<ImHashMap<FinancialYear, f64>>, Option<String>) {
     let mut transactions_files = Vec::new();
     for data_picker_state in data.data_sources.iter() {
         let reader_type = ReaderType::from_str(&data_picker_state.reader_type).unwrap();
         let transactions_file = TransactionsFile {
             path: data_picker_state.path.clone(),
-            reader_type,
+            reader_type: Some(reader_type),
+            // `Generic` isn't offered in the reader dropdown above (it needs a
+            // sidecar config the GUI has no picker for), so every other reader here
+            // correctly needs no config path.
+            reader_config_path: None,
         };
         transactions_files.push(transactions_file);
     }
     let calculator_type = CalculatorType::from_str(&data.calculator_type).unwrap();
-    let capital_gains = calculate_capital_gains(transactions_files, calculator_type);
+    // The GUI only shows totals today, so it doesn't need the per-disposal audit trail.
+    // It also doesn't expose an entity type picker yet, so assume the common case.
+    let capital_gains = calculate_capital_gains(
+        transactions_files,
+        calculator_type,
+        EntityType::Individual,
+        false,
+    );
+    let (capital_gains, error_text) = match capital_gains {
+        Ok(result) => {
+            let by_fy: HashMap<FinancialYear, f64> = result
+                .totals
+                .into_iter()
+                .map(|(year, gain)| (year, gain.net_gain.to_f64().unwrap_or(0.0)))
+                .collect();
+            (Some(ImHashMap::from(&by_fy)), None)
+        }
+        Err(e) => (None, Some(format!("{:#}", e))),
+    };
+    (capital_gains, error_text)
+}
+
+fn calculate_capital_gains_from_exchange_local(
+    data: &InitialState,
+) -> (Option<ImHashMap<FinancialYear, f64>>, Option<String>) {
+    let exchange_type = ExchangeType::from_str(&data.exchange_type).unwrap();
+    let credentials_path = match &data.credentials_path {
+        Some(path) => path.clone(),
+        None => return (None, Some("No credentials file selected".to_string())),
+    };
+    let calculator_type = CalculatorType::from_str(&data.calculator_type).unwrap();
+    // Druid's event loop is synchronous, so block on the async fetch here rather
+    // than threading a runtime through the whole GUI. Also assume the common entity
+    // type, as above, since there's no picker for it yet.
+    let capital_gains = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(calculate_capital_gains_from_api(
+            exchange_type,
+            credentials_path,
+            calculator_type,
+            EntityType::Individual,
+            false,
+        )),
+        Err(e) => Err(e).context("Failed to start async runtime"),
+    };
     let (capital_gains, error_text) = match capital_gains {
-        Ok(cg) => (Some(ImHashMap::from(&cg)), None),
+        Ok(result) => {
+            let by_fy: HashMap<FinancialYear, f64> = result
+                .totals
+                .into_iter()
+                .map(|(year, gain)| (year, gain.net_gain.to_f64().unwrap_or(0.0)))
+                .collect();
+            (Some(ImHashMap::from(&by_fy)), None)
+        }
         Err(e) => (None, Some(format!("{:#}", e))),
     };
     (capital_gains, error_text)
@@ -274,17 +420,23 @@ fn build_data_picker_widget() -> impl Widget<DataPickerState> {
     // Name of the file.
     let path_end_label = Label::new(|data: &DataPickerState, _env: &Env| data.get_path_end());
 
-    // Dropdown for choosing which kind of reader to use.
+    // Dropdown for choosing which kind of reader to use. `Generic` is left out: it
+    // needs a sidecar config path (see `generic_csv`), and the GUI has no picker for
+    // one, so offering it here would only let the user pick a reader that's
+    // guaranteed to fail once they hit "Calculate". It's still usable from the CLI,
+    // which does have a flag for the sidecar config path.
     let dropdown = Dropdown::new(
         Button::new(|reader_type_string: &String, _: &Env| reader_type_string.to_string())
             .on_click(|ctx: &mut EventCtx, _, _| ctx.submit_notification(DROPDOWN_SHOW)),
         |_, _| {
+            let selectable: Vec<&str> = ReaderType::variants()
+                .iter()
+                .copied()
+                .filter(|variant| *variant != "Generic")
+                .collect();
             let choices: Vec<(&str, String)> = izip!(
-                ReaderType::variants(),
-                ReaderType::variants()
-                    .iter()
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>()
+                selectable.clone(),
+                selectable.iter().map(|s| s.to_string()).collect::<Vec<String>>()
             )
             .collect();
             RadioGroup::new(choices)
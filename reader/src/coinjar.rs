@@ -3,14 +3,16 @@ use anyhow::Result;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use csv::Reader as CsvReader;
 use log::trace;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use serde::{Deserialize, Deserializer};
 use std::path::PathBuf;
 use types::{Currency, Transaction, TransactionType};
 
-fn comma_float<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+fn comma_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
     let buf = String::deserialize(deserializer)?;
     let buf = buf.replace(",", "");
-    let num = buf.parse::<f64>();
+    let num = buf.parse::<Decimal>();
     match num {
         Ok(num) => Ok(num),
         Err(e) => Err(serde::de::Error::custom(e)),
@@ -20,40 +22,94 @@ fn comma_float<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Err
 #[derive(Debug, Deserialize)]
 struct Row {
     transacted_at: String,
-    #[serde(deserialize_with = "comma_float")]
-    debit: f64,
+    #[serde(deserialize_with = "comma_decimal")]
+    debit: Decimal,
     currency: String,
-    #[serde(deserialize_with = "comma_float")]
-    counterparty_amount: f64,
+    #[serde(deserialize_with = "comma_decimal")]
+    counterparty_amount: Decimal,
     counterparty_currency: String,
     rates: String,
-    #[serde(deserialize_with = "comma_float")]
-    fee_amount: f64,
+    #[serde(deserialize_with = "comma_decimal")]
+    fee_amount: Decimal,
 }
 
-impl Into<Transaction> for Row {
-    fn into(self) -> Transaction {
+// Coinjar usually trades straight against AUD, but it also exports crypto-to-crypto
+// conversions where neither side is AUD. A row like that is itself a CGT event: a
+// disposal of `currency` and a simultaneous acquisition of `counterparty_currency`,
+// both valued at the same AUD amount. So one row can produce two `Transaction`s.
+impl Row {
+    // Coinjar's export has no id column of its own, so `index` (the row's position
+    // in the file) is carried into `Transaction::id` as a stand-in: stable for a
+    // given export, but not a real exchange-assigned id.
+    fn into_transactions(self, index: usize) -> Vec<Transaction> {
         let rate: String = self.rates.split(" = $").collect::<Vec<_>>()[1]
             .split_whitespace()
             .collect::<Vec<_>>()[0]
             .replace(",", "");
-        let rate: f64 = rate
-            .parse::<f64>()
-            .expect(&format!("Failed to parse rate string {} as float", rate));
-        // This ignores fees for now.
-        let (currency, transaction_type) = match self.currency == "AUD".to_string() {
-            true => (self.counterparty_currency, TransactionType::Buy),
-            false => (self.currency, TransactionType::Sell),
-        };
-        let amount = match transaction_type {
-            TransactionType::Buy => self.counterparty_amount,
-            TransactionType::Sell => self.debit,
-        };
+        let rate: Decimal = rate
+            .parse::<Decimal>()
+            .expect(&format!("Failed to parse rate string {} as decimal", rate));
+        // Coinjar quotes fees in the crypto asset being traded, so convert to AUD
+        // using the same rate as the rest of the transaction.
+        let fee_aud = self.fee_amount * rate;
         let ndt = NaiveDateTime::parse_from_str(&self.transacted_at, "%Y-%m-%d %H:%M:%S %Z")
             .expect("Failed to parse timestamp");
         let dt = DateTime::<Utc>::from_utc(ndt, Utc);
         let unixtime = dt.timestamp() as u64;
-        Transaction::new(amount, Currency(currency), rate, transaction_type, unixtime)
+
+        let currency_is_aud = self.currency == "AUD";
+        let counterparty_is_aud = self.counterparty_currency == "AUD";
+
+        if currency_is_aud {
+            // Buying `counterparty_currency` with AUD.
+            vec![Transaction::new(
+                format!("row-{}", index),
+                self.counterparty_amount,
+                Currency(self.counterparty_currency),
+                rate,
+                fee_aud,
+                TransactionType::Buy,
+                unixtime,
+            )]
+        } else if counterparty_is_aud {
+            // Selling `currency` for AUD.
+            vec![Transaction::new(
+                format!("row-{}", index),
+                self.debit,
+                Currency(self.currency),
+                rate,
+                fee_aud,
+                TransactionType::Sell,
+                unixtime,
+            )]
+        } else {
+            // Neither side is AUD: a crypto-to-crypto trade. This disposes of
+            // `currency` and acquires `counterparty_currency`, both valued at the
+            // same AUD amount (the apportioned fee is only applied to the disposed
+            // side, to keep this from double counting it).
+            let aud_value = self.debit * rate;
+            let counterparty_rate = aud_value / self.counterparty_amount;
+            vec![
+                Transaction::new(
+                    format!("row-{}-sell", index),
+                    self.debit,
+                    Currency(self.currency),
+                    rate,
+                    fee_aud,
+                    TransactionType::Sell,
+                    unixtime,
+                ),
+                Transaction::new(
+                    format!("row-{}-buy", index),
+                    self.counterparty_amount,
+                    Currency(self.counterparty_currency),
+                    counterparty_rate,
+                    dec!(0),
+                    TransactionType::Buy,
+                    unixtime,
+                ),
+            ]
+        }
     }
 }
 
@@ -73,7 +129,11 @@ impl Reader for CoinjarReader {
             };
             rows.push(row);
         }
-        let transactions: Vec<Transaction> = rows.into_iter().map(|r| r.into()).collect();
+        let transactions: Vec<Transaction> = rows
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, r)| r.into_transactions(index))
+            .collect();
         Ok(transactions)
     }
 }
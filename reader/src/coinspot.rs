@@ -0,0 +1,112 @@
+use crate::traits::Reader;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use csv::Reader as CsvReader;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::path::PathBuf;
+use types::{Currency, Transaction, TransactionType};
+
+/// The column headers CoinSpot's "Buys/Sells" CSV export uses, in order. There's no
+/// explicit version field in the export itself, so this header row doubles as the
+/// schema's version: if CoinSpot ever adds, removes or reorders a column, the header
+/// read off disk won't match this anymore, and `CoinSpotReader` fails loudly instead
+/// of silently mis-mapping columns. Also used by `ReaderType::detect` to recognise a
+/// CoinSpot export by its header row.
+pub const EXPECTED_HEADER: &[&str] = &[
+    "Transaction ID",
+    "Date",
+    "Market",
+    "Type",
+    "Amount",
+    "Rate",
+    "Total",
+    "Fee",
+];
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    // CoinSpot's own id for the transaction, stable across re-exports. Carried into
+    // `Transaction::id` so a `Reversal` transaction can name a specific CoinSpot
+    // transaction to void.
+    #[serde(rename = "Transaction ID")]
+    transaction_id: String,
+
+    #[serde(rename = "Date")]
+    date: String,
+
+    // e.g. "BTC/AUD". CoinSpot only ever trades crypto against AUD, so the currency
+    // code is always the part before the slash.
+    #[serde(rename = "Market")]
+    market: String,
+
+    #[serde(rename = "Type")]
+    transaction_type: String,
+
+    // Amount of crypto bought or sold.
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+
+    // AUD conversion rate.
+    #[serde(rename = "Rate")]
+    rate: Decimal,
+
+    // AUD fee charged on the transaction.
+    #[serde(rename = "Fee")]
+    fee: Decimal,
+}
+
+impl Into<Transaction> for Row {
+    fn into(self) -> Transaction {
+        let currency = self
+            .market
+            .split('/')
+            .next()
+            .expect("split always yields at least one piece")
+            .to_string();
+        let ndt = NaiveDateTime::parse_from_str(&self.date, "%d/%m/%Y %H:%M")
+            .expect("Failed to parse timestamp");
+        let unixtime = DateTime::<Utc>::from_utc(ndt, Utc).timestamp() as u64;
+        let transaction_type = match self.transaction_type.as_str() {
+            "Buy" => TransactionType::Buy,
+            "Sell" => TransactionType::Sell,
+            other => panic!("Unexpected CoinSpot transaction type {:?}", other),
+        };
+        Transaction::new(
+            self.transaction_id,
+            self.amount,
+            Currency(currency),
+            self.rate,
+            self.fee,
+            transaction_type,
+            unixtime,
+        )
+    }
+}
+
+/// A `Reader` for CoinSpot's "Buys/Sells" CSV export. Unlike `GenericCsvReader`,
+/// CoinSpot doesn't split each row into separate base/counterparty currency columns:
+/// it's always a crypto/AUD pair, with the side (buy or sell) given directly by the
+/// `Type` column. That doesn't fit the generic reader's AUD-side-detection model, so
+/// this reader maps rows directly, the same way `coinjar::Row` does.
+pub struct CoinSpotReader {}
+
+impl Reader for CoinSpotReader {
+    fn read_transactions(&self, path: &PathBuf) -> Result<Vec<Transaction>> {
+        let mut rdr = CsvReader::from_path(path)?;
+        let header = rdr.headers()?.clone();
+        if header.iter().collect::<Vec<_>>() != EXPECTED_HEADER {
+            bail!(
+                "Unrecognised CoinSpot CSV header {:?}; this build only understands the layout {:?}",
+                header, EXPECTED_HEADER
+            );
+        }
+
+        let mut transactions = Vec::new();
+        for result in rdr.deserialize() {
+            let row: Row = result.context("Failed to parse CoinSpot CSV row")?;
+            transactions.push(row.into());
+        }
+        Ok(transactions)
+    }
+}
@@ -0,0 +1,219 @@
+use crate::traits::Reader;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use csv::{Reader as CsvReader, StringRecord};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::fs::File;
+use std::path::PathBuf;
+use types::{Currency, Transaction, TransactionType};
+
+fn default_aud_currency() -> String {
+    "AUD".to_string()
+}
+
+/// The only `schema_version` this reader currently understands. Bumped whenever a
+/// `GenericCsvConfig` field is added, removed or changes meaning, so an older config
+/// written against a previous layout fails loudly instead of being silently
+/// misinterpreted.
+pub const SUPPORTED_GENERIC_CSV_SCHEMA_VERSION: u32 = 1;
+
+/// Describes how to map an arbitrary exchange's CSV export onto `Transaction`s, the
+/// same way `coinjar::Row` does for Coinjar's export format. Wiring up a new exchange
+/// whose export has separate base and counterparty currency columns only requires
+/// writing one of these files rather than a new `Reader` impl and `ReaderType` variant.
+///
+/// As with Coinjar's export, each row has a "base" side and a "counterparty" side.
+/// Whichever side is AUD determines whether the row is a Buy (of the counterparty
+/// currency) or a Sell (of the base currency). A row where neither side is AUD is a
+/// crypto-to-crypto trade and becomes both a Sell of the base currency and a Buy of
+/// the counterparty currency.
+#[derive(Debug, Deserialize)]
+pub struct GenericCsvConfig {
+    /// The version of this schema the config file was written against. Checked
+    /// against `SUPPORTED_GENERIC_CSV_SCHEMA_VERSION` when the config is loaded, so
+    /// a config written for a newer, incompatible layout fails loudly rather than
+    /// silently mis-mapping columns.
+    pub schema_version: u32,
+
+    /// Column holding the transaction timestamp.
+    pub timestamp_column: String,
+
+    /// `chrono` format string used to parse `timestamp_column`, e.g. "%Y-%m-%d %H:%M:%S".
+    pub timestamp_format: String,
+
+    /// Column holding the amount on the base side of the trade.
+    pub base_amount_column: String,
+
+    /// Column holding the currency code on the base side of the trade.
+    pub base_currency_column: String,
+
+    /// Column holding the amount on the counterparty side of the trade.
+    pub counterparty_amount_column: String,
+
+    /// Column holding the currency code on the counterparty side of the trade.
+    pub counterparty_currency_column: String,
+
+    /// Column holding the AUD conversion rate applied to the non-AUD side.
+    pub rate_column: String,
+
+    /// Column holding the fee paid on the transaction, if present. Quoted in the
+    /// traded asset and converted to AUD at `rate_column`'s rate, as with Coinjar.
+    pub fee_column: Option<String>,
+
+    /// The currency code that denotes the AUD side of a trade.
+    #[serde(default = "default_aud_currency")]
+    pub aud_currency: String,
+}
+
+impl GenericCsvConfig {
+    pub fn load(path: &PathBuf) -> Result<GenericCsvConfig> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open generic CSV config at {:?}", path))?;
+        let config: GenericCsvConfig = ron::de::from_reader(file)
+            .with_context(|| format!("Failed to parse generic CSV config at {:?}", path))?;
+        config.check_schema_version()?;
+        Ok(config)
+    }
+
+    fn check_schema_version(&self) -> Result<()> {
+        if self.schema_version != SUPPORTED_GENERIC_CSV_SCHEMA_VERSION {
+            bail!(
+                "Unsupported generic CSV config schema version {}; this build only understands version {}",
+                self.schema_version, SUPPORTED_GENERIC_CSV_SCHEMA_VERSION
+            );
+        }
+        Ok(())
+    }
+}
+
+pub struct GenericCsvReader {
+    config: GenericCsvConfig,
+}
+
+impl GenericCsvReader {
+    pub fn new(config_path: &PathBuf) -> Result<GenericCsvReader> {
+        Ok(GenericCsvReader {
+            config: GenericCsvConfig::load(config_path)?,
+        })
+    }
+
+    fn column<'a>(
+        &self,
+        headers: &StringRecord,
+        record: &'a StringRecord,
+        column: &str,
+    ) -> Result<&'a str> {
+        let index = headers
+            .iter()
+            .position(|h| h == column)
+            .with_context(|| format!("Column {:?} not present in CSV header {:?}", column, headers))?;
+        record
+            .get(index)
+            .with_context(|| format!("Row {:?} missing a value for column {:?}", record, column))
+    }
+
+    // Reuses the same thousands-separator-stripping approach as the Coinjar reader.
+    fn parse_decimal(raw: &str) -> Result<Decimal> {
+        raw.replace(",", "")
+            .parse::<Decimal>()
+            .with_context(|| format!("Failed to parse {:?} as a decimal", raw))
+    }
+}
+
+impl Reader for GenericCsvReader {
+    fn read_transactions(&self, path: &PathBuf) -> Result<Vec<Transaction>> {
+        let mut rdr: CsvReader<File> = CsvReader::from_path(path)?;
+        let headers = rdr.headers()?.clone();
+
+        let mut transactions = Vec::new();
+        for (index, result) in rdr.records().enumerate() {
+            let record = result?;
+
+            let timestamp_raw = self.column(&headers, &record, &self.config.timestamp_column)?;
+            let ndt = NaiveDateTime::parse_from_str(timestamp_raw, &self.config.timestamp_format)
+                .with_context(|| format!("Failed to parse timestamp {:?}", timestamp_raw))?;
+            let unixtime = DateTime::<Utc>::from_utc(ndt, Utc).timestamp() as u64;
+
+            let base_currency = self.column(&headers, &record, &self.config.base_currency_column)?;
+            let counterparty_currency =
+                self.column(&headers, &record, &self.config.counterparty_currency_column)?;
+            let rate =
+                Self::parse_decimal(self.column(&headers, &record, &self.config.rate_column)?)?;
+            let base_amount =
+                Self::parse_decimal(self.column(&headers, &record, &self.config.base_amount_column)?)?;
+            let counterparty_amount = Self::parse_decimal(self.column(
+                &headers,
+                &record,
+                &self.config.counterparty_amount_column,
+            )?)?;
+
+            // Fees are quoted in the same currency as the traded asset, same as Coinjar.
+            let fee_aud = match &self.config.fee_column {
+                Some(fee_column) => {
+                    Self::parse_decimal(self.column(&headers, &record, fee_column)?)? * rate
+                }
+                None => Decimal::ZERO,
+            };
+
+            let base_is_aud = base_currency == self.config.aud_currency;
+            let counterparty_is_aud = counterparty_currency == self.config.aud_currency;
+
+            // The config describes an arbitrary exchange's columns, and has no
+            // notion of an id column, so there's no exchange-supplied id to carry
+            // forward here the way `coinspot::Row` or `api::Fill` do. The row's
+            // position in the file is used instead: stable for a given export, but
+            // (unlike a real exchange id) not guaranteed to still line up if the
+            // export is later re-downloaded with extra rows inserted earlier.
+            if base_is_aud {
+                // Buying the counterparty currency with AUD.
+                transactions.push(Transaction::new(
+                    format!("row-{}", index),
+                    counterparty_amount,
+                    Currency(counterparty_currency.to_string()),
+                    rate,
+                    fee_aud,
+                    TransactionType::Buy,
+                    unixtime,
+                ));
+            } else if counterparty_is_aud {
+                // Selling the base currency for AUD.
+                transactions.push(Transaction::new(
+                    format!("row-{}", index),
+                    base_amount,
+                    Currency(base_currency.to_string()),
+                    rate,
+                    fee_aud,
+                    TransactionType::Sell,
+                    unixtime,
+                ));
+            } else {
+                // Neither side is AUD: a crypto-to-crypto trade. This disposes of the
+                // base currency and acquires the counterparty currency, both valued
+                // at the same AUD amount (the fee is only applied to the disposed
+                // side, to avoid double counting it).
+                let aud_value = base_amount * rate;
+                let counterparty_rate = aud_value / counterparty_amount;
+                transactions.push(Transaction::new(
+                    format!("row-{}-sell", index),
+                    base_amount,
+                    Currency(base_currency.to_string()),
+                    rate,
+                    fee_aud,
+                    TransactionType::Sell,
+                    unixtime,
+                ));
+                transactions.push(Transaction::new(
+                    format!("row-{}-buy", index),
+                    counterparty_amount,
+                    Currency(counterparty_currency.to_string()),
+                    counterparty_rate,
+                    Decimal::ZERO,
+                    TransactionType::Buy,
+                    unixtime,
+                ));
+            }
+        }
+        Ok(transactions)
+    }
+}
@@ -1,12 +1,24 @@
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
 use structopt::clap::arg_enum;
 use types::{Transaction, TransactionType};
 
+mod api;
+mod binance;
 mod coinjar;
 mod coinjar_simple;
+mod coinspot;
+mod generic_csv;
+mod json;
 mod traits;
 
+pub use crate::api::{ApiCredentials, ApiReader, GenericExchangeApiReader};
+use crate::binance::BinanceReader;
 use crate::coinjar::CoinjarReader;
 use crate::coinjar_simple::CoinjarSimpleReader;
+use crate::coinspot::CoinSpotReader;
+use crate::generic_csv::GenericCsvReader;
+use crate::json::JsonReader;
 use crate::traits::Reader;
 
 arg_enum! {
@@ -15,14 +27,106 @@ arg_enum! {
 pub enum ReaderType {
     Coinjar,
     CoinjarSimple,
+    CoinSpot,
+    Binance,
+    Json,
+    Generic,
+    // A placeholder so `Api` shows up next to the other readers in the CLI/GUI
+    // dropdowns. It can't actually be constructed via `get_reader`, since fetching
+    // from an exchange API is async and needs credentials rather than a file path.
+    // Use `ExchangeType` and `ApiReader` via the async entry point instead.
+    Api,
 }
 }
 
 impl ReaderType {
-    pub fn get_reader(&self) -> Box<dyn Reader> {
+    /// Construct the `Reader` for this variant. `Generic` requires a sidecar RON
+    /// config describing the exchange's column layout (see `generic_csv`); other
+    /// readers ignore it.
+    pub fn get_reader(&self, config_path: Option<&PathBuf>) -> Result<Box<dyn Reader>> {
         match &self {
-            Self::Coinjar => Box::new(coinjar::CoinjarReader {}),
-            Self::CoinjarSimple => Box::new(coinjar_simple::CoinjarSimpleReader {}),
+            Self::Coinjar => Ok(Box::new(coinjar::CoinjarReader {})),
+            Self::CoinjarSimple => Ok(Box::new(coinjar_simple::CoinjarSimpleReader {})),
+            Self::CoinSpot => Ok(Box::new(CoinSpotReader {})),
+            Self::Binance => Ok(Box::new(BinanceReader {})),
+            Self::Json => Ok(Box::new(JsonReader {})),
+            Self::Generic => {
+                let config_path = config_path.ok_or_else(|| {
+                    anyhow::anyhow!("ReaderType::Generic requires a sidecar config path")
+                })?;
+                Ok(Box::new(GenericCsvReader::new(config_path)?))
+            }
+            Self::Api => bail!(
+                "ReaderType::Api can't be used with file-based paths; use \
+                 ExchangeType and the async API entry point instead"
+            ),
+        }
+    }
+
+    /// Guess which `ReaderType` a transactions file needs, for callers that don't
+    /// want to name one explicitly. A `.json` extension means `Json`; otherwise the
+    /// file is assumed to be CSV and its header row is matched against each concrete
+    /// CSV reader's expected header. There's no sensible way to auto-detect
+    /// `Generic`, since it has no fixed header of its own, or `Api`, which doesn't
+    /// read from a file at all, so neither is ever returned here.
+    pub fn detect(path: &PathBuf) -> Result<ReaderType> {
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            return Ok(ReaderType::Json);
+        }
+
+        let mut rdr = csv::Reader::from_path(path)
+            .with_context(|| format!("Failed to open {:?} to detect its reader", path))?;
+        let header: Vec<String> = rdr
+            .headers()
+            .with_context(|| format!("Failed to read the header row of {:?}", path))?
+            .iter()
+            .map(str::to_string)
+            .collect();
+        let header: Vec<&str> = header.iter().map(String::as_str).collect();
+
+        if header == coinspot::EXPECTED_HEADER {
+            Ok(ReaderType::CoinSpot)
+        } else if header == binance::EXPECTED_HEADER {
+            Ok(ReaderType::Binance)
+        } else {
+            bail!(
+                "Could not auto-detect a reader for {:?} from its header {:?}; pass --readers explicitly",
+                path, header
+            )
+        }
+    }
+}
+
+arg_enum! {
+/// This enum registers the exchanges we can fetch transaction history from live,
+/// over their REST API, instead of from a CSV export.
+///
+/// Experimental: no variant is wired to a real exchange yet (see
+/// `get_api_reader`), so the CLI's `--exchange`/`--credentials` flags and the
+/// GUI's "Calculate from exchange" button are hidden/disabled rather than
+/// presented as a working feature.
+#[derive(Clone, Debug)]
+pub enum ExchangeType {
+    // Not wired to any real exchange: `GenericExchangeApiReader` models a generic
+    // key/secret-authenticated, cursor-paginated fills endpoint, but no such
+    // endpoint (with its auth scheme, e.g. HMAC request signing) is documented
+    // here. `get_api_reader` refuses to construct one rather than pointing it at
+    // a placeholder host. Kept in the enum so `--exchange`'s possible-values list
+    // documents the shape callers should fill in once a real exchange is wired up.
+    Generic,
+}
+}
+
+impl ExchangeType {
+    pub fn get_api_reader(&self) -> Result<Box<dyn ApiReader>> {
+        match &self {
+            Self::Generic => bail!(
+                "ExchangeType::Generic is not wired to a real exchange API yet; \
+                 GenericExchangeApiReader is a model of the expected key/secret \
+                 cursor-paginated shape, not a working integration. Wire it to a \
+                 real exchange's documented auth (e.g. HMAC request signing) and \
+                 pagination before using --exchange"
+            ),
         }
     }
 }
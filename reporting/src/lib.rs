@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use chrono::{TimeZone, Utc};
+use prettytable::{cell, row, Table};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use std::path::Path;
+use types::{Currency, Disposal, FinancialYear};
+
+fn format_date(unixtime: u64) -> String {
+    Utc.timestamp(unixtime as i64, 0)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// Builds one `prettytable` table per financial year for a currency's disposals,
+/// each with a subtotal row, suitable for printing to stdout or a file.
+///
+/// This is the audit trail a user needs to substantiate a return to the ATO: every
+/// disposal, the buy lot it was matched against, and the resulting gain or loss.
+pub fn render_disposal_tables(
+    disposals_by_year: &HashMap<FinancialYear, Vec<Disposal>>,
+) -> Vec<(FinancialYear, Table)> {
+    let mut years: Vec<&FinancialYear> = disposals_by_year.keys().collect();
+    years.sort();
+
+    let mut tables = Vec::new();
+    for year in years {
+        let disposals = &disposals_by_year[year];
+        let mut table = Table::new();
+        table.set_titles(row![
+            "Acquired",
+            "Disposed",
+            "Quantity",
+            "Cost base (AUD)",
+            "Proceeds (AUD)",
+            "Gain/loss (AUD)",
+            "Discount eligible"
+        ]);
+
+        let mut subtotal = dec!(0);
+        for disposal in disposals {
+            table.add_row(row![
+                format_date(disposal.buy_unixtime),
+                format_date(disposal.sell_unixtime),
+                disposal.quantity,
+                format!("{:.2}", disposal.cost_base_aud),
+                format!("{:.2}", disposal.proceeds_aud),
+                format!("{:.2}", disposal.gain),
+                if disposal.discount_eligible { "yes" } else { "no" }
+            ]);
+            subtotal += disposal.gain;
+        }
+        table.add_row(row![
+            "",
+            "",
+            "",
+            "",
+            "",
+            format!("Subtotal: {:.2}", subtotal),
+            ""
+        ]);
+
+        tables.push((*year, table));
+    }
+
+    tables
+}
+
+/// Prints a currency's disposals to stdout, one table per financial year.
+pub fn print_disposals(currency: &Currency, disposals_by_year: &HashMap<FinancialYear, Vec<Disposal>>) {
+    for (year, table) in render_disposal_tables(disposals_by_year) {
+        println!("{} disposals for {}", currency.0, year);
+        table.printstd();
+        println!();
+    }
+}
+
+/// Writes a currency's disposals to a CSV file, one row per disposal across all
+/// financial years, with a `financial_year` column so the file can still be filtered
+/// or pivoted.
+pub fn write_disposals_csv(
+    path: &Path,
+    currency: &Currency,
+    disposals_by_year: &HashMap<FinancialYear, Vec<Disposal>>,
+) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to create report CSV at {:?}", path))?;
+    writer.write_record(&[
+        "currency",
+        "financial_year",
+        "acquired",
+        "disposed",
+        "quantity",
+        "cost_base_aud",
+        "proceeds_aud",
+        "gain_aud",
+        "discount_eligible",
+    ])?;
+
+    let mut years: Vec<&FinancialYear> = disposals_by_year.keys().collect();
+    years.sort();
+    for year in years {
+        for disposal in &disposals_by_year[year] {
+            writer.write_record(&[
+                currency.0.clone(),
+                year.to_string(),
+                format_date(disposal.buy_unixtime),
+                format_date(disposal.sell_unixtime),
+                disposal.quantity.to_string(),
+                round_cents(disposal.cost_base_aud).to_string(),
+                round_cents(disposal.proceeds_aud).to_string(),
+                round_cents(disposal.gain).to_string(),
+                disposal.discount_eligible.to_string(),
+            ])?;
+        }
+    }
+    writer.flush().context("Failed to flush report CSV")?;
+    Ok(())
+}
+
+fn round_cents(value: Decimal) -> Decimal {
+    value.round_dp(2)
+}
@@ -1,19 +1,136 @@
-use chrono::{DateTime, TimeZone};
+use chrono::{Datelike, TimeZone, Utc};
 use log::debug;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::fmt;
+
+// Number of seconds an asset must be held for a disposal to be eligible
+// for the Australian 12 month CGT discount.
+const DISCOUNT_HOLDING_PERIOD_SECONDS: u64 = 365 * 24 * 60 * 60;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Currency(pub String);
 
+/// An Australian financial year, running 1 July to 30 June. The inner value
+/// is the calendar year the financial year ends in, e.g. the financial year
+/// 2022-07-01 to 2023-06-30 is `FinancialYear(2023)`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct FinancialYear(pub i32);
+
+impl FinancialYear {
+    /// Determine which Australian financial year a given unixtime falls in.
+    pub fn containing(unixtime: u64) -> FinancialYear {
+        let dt = Utc.timestamp(unixtime as i64, 0);
+        let calendar_year = dt.year();
+        if dt.month() >= 7 {
+            FinancialYear(calendar_year + 1)
+        } else {
+            FinancialYear(calendar_year)
+        }
+    }
+}
+
+impl fmt::Display for FinancialYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FY{}-{}", self.0 - 1, self.0)
+    }
+}
+
+/// The result of matching a sell against a single buy lot (or part thereof).
+/// `FifoCalculator` accumulates these as it processes a sell, since a single
+/// sell can consume multiple buy lots.
+#[derive(Clone, Debug)]
+pub struct Disposal {
+    // When the disposed-of asset was originally acquired.
+    pub buy_unixtime: u64,
+
+    // When the asset was disposed of.
+    pub sell_unixtime: u64,
+
+    // How much of the asset this disposal covers.
+    pub quantity: Decimal,
+
+    // The cost base in AUD of the quantity disposed of, including any apportioned
+    // buy-side fee.
+    pub cost_base_aud: Decimal,
+
+    // The proceeds in AUD from disposing of the quantity, net of any apportioned
+    // sell-side fee.
+    pub proceeds_aud: Decimal,
+
+    // The raw (pre-discount) capital gain or loss in AUD for this disposal.
+    pub gain: Decimal,
+
+    // Whether this disposal held the asset for longer than 12 months and is
+    // therefore eligible for the Australian CGT discount.
+    pub discount_eligible: bool,
+}
+
+impl Disposal {
+    pub fn financial_year(&self) -> FinancialYear {
+        FinancialYear::containing(self.sell_unixtime)
+    }
+}
+
+/// The net assessable capital gain for a single financial year, together with the
+/// gross buckets it was netted down from. Losses are netted against
+/// `non_discounted_gains` before `discounted_gains` (to preserve as much of the
+/// discountable gain as possible) and the discount is applied last, so `net_gain`
+/// alone can't be un-derived from `discounted_gains - losses`; callers that need to
+/// substantiate the figure should keep the buckets alongside it.
+#[derive(Clone, Copy, Debug)]
+pub struct FinancialYearGain {
+    /// Gross gains from disposals eligible for the 12 month CGT discount, before
+    /// losses or the discount are applied.
+    pub discounted_gains: Decimal,
+
+    /// Gross gains from disposals not eligible for the discount, before losses are
+    /// applied.
+    pub non_discounted_gains: Decimal,
+
+    /// Gross capital losses for the year, including any net capital loss carried
+    /// forward from earlier financial years.
+    pub losses: Decimal,
+
+    /// The net assessable capital gain after netting losses and applying the
+    /// discount, i.e. the figure that actually goes on the return.
+    pub net_gain: Decimal,
+
+    /// The part of `losses` that exceeded this year's gains and so carries
+    /// forward to offset a future financial year's gains instead of being
+    /// applied this year. Capital losses never expire under ATO rules, so this
+    /// is never simply discarded.
+    pub losses_carried_forward: Decimal,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum TransactionType {
     Buy,
     Sell,
+
+    /// A correction that voids the effect of an earlier transaction, identified by
+    /// its `Transaction::id`, e.g. an exchange unwinding an erroneous trade. Carries
+    /// the id of the transaction it reverses.
+    Reversal(String),
 }
 
 #[derive(Clone, Debug)]
 pub struct Transaction {
-    // Amount of the transaction. Always a postive number.
-    amount: f64,
+    // A stable id for this transaction, supplied by whatever read it in (e.g. an
+    // exchange export's own transaction id column, or an id a hand-written JSON
+    // correction names explicitly), not generated at construction time. Lets a
+    // `Reversal` transaction reference the transaction it voids by an id that
+    // means the same thing every time the same input is read, not just within one
+    // process's lifetime.
+    pub id: String,
+
+    // Amount of the transaction. Always a postive number. Decreases as this
+    // transaction is matched against the other side of the ledger.
+    amount: Decimal,
+
+    // The amount this transaction started out with, before any matching. Used to
+    // apportion `fee_aud` across the sub-lots a transaction gets split into.
+    original_amount: Decimal,
 
     // Currency the transaction was made in.
     // The other side of the transaction is assumed to be AUD.
@@ -21,7 +138,10 @@ pub struct Transaction {
 
     // Conversion rate of currency to AUD.
     // e.g. If 1 BTC costs 40,000 AUD, this would be 40,000.
-    rate: f64,
+    rate: Decimal,
+
+    // The fee charged on this transaction, in AUD, for its full original amount.
+    fee_aud: Decimal,
 
     // Whether this was a buy or sell.
     pub transaction_type: TransactionType,
@@ -32,26 +152,39 @@ pub struct Transaction {
 
 impl Transaction {
     pub fn new(
-        amount: f64,
+        id: String,
+        amount: Decimal,
         currency: Currency,
-        rate: f64,
+        rate: Decimal,
+        fee_aud: Decimal,
         transaction_type: TransactionType,
         unixtime: u64,
     ) -> Transaction {
         Transaction {
+            id,
             amount,
+            original_amount: amount,
             currency,
             rate,
+            fee_aud,
             transaction_type,
             unixtime,
         }
     }
 
     /// Returns true if this transaction has nothing left in it.
-    /// We check for less than a small amount instead of 0 to deal
-    /// with floating point arithmetic inaccuracy.
+    /// Decimal arithmetic is exact, so we can compare against a true zero
+    /// instead of the epsilon a float representation would have required.
     pub fn is_exhausted(&self) -> bool {
-        self.amount < 0.00001
+        self.amount <= dec!(0)
+    }
+
+    /// Returns true if nothing has matched against this transaction yet, i.e. it is
+    /// still sitting in the lot queue untouched. A `Reversal` can only safely void a
+    /// buy while this holds; once a buy has started being consumed, silently
+    /// removing it would understate an already-recorded disposal.
+    pub fn is_untouched(&self) -> bool {
+        self.amount == self.original_amount
     }
 
     ///  Return from this function: (
@@ -72,8 +205,10 @@ impl Transaction {
     /// The calling code should throw out whichever Transaction is None as a result of
     /// this function.
     ///
-    /// Returns the capital gain in terms of AUD.
-    pub fn subtract_sell(&mut self, other: &mut Transaction) -> f64 {
+    /// Returns a `Disposal` describing the matched buy/sell pair: the capital gain in
+    /// terms of AUD (computed exactly, only rounded to cents when actually reported)
+    /// and whether the disposal qualifies for the 12 month CGT discount.
+    pub fn subtract_sell(&mut self, other: &mut Transaction) -> Disposal {
         if !(self.transaction_type == TransactionType::Buy
             && other.transaction_type == TransactionType::Sell)
         {
@@ -94,17 +229,142 @@ impl Transaction {
             other, self, delta
         );
         let remaining_buy = self.amount - other.amount;
-        let buy_in_aud = delta * self.rate;
-        let sell_in_aud = delta * other.rate;
-        if remaining_buy > 0.0 {
+        // Apportion each side's total fee across its sub-lots in proportion to how
+        // much of that transaction's original amount this match consumes.
+        let apportioned_buy_fee = self.fee_aud * (delta / self.original_amount);
+        let apportioned_sell_fee = other.fee_aud * (delta / other.original_amount);
+        let buy_in_aud = delta * self.rate + apportioned_buy_fee;
+        let sell_in_aud = delta * other.rate - apportioned_sell_fee;
+        if remaining_buy > dec!(0) {
             self.amount -= other.amount;
-            other.amount = 0.0;
+            other.amount = dec!(0);
         } else {
             other.amount -= self.amount;
-            self.amount = 0.0;
+            self.amount = dec!(0);
         }
         let capital_gain = sell_in_aud - buy_in_aud;
         debug!("Buy is now {:?} and sell is now {:?}, capital gain is {}", self, other, capital_gain);
-        capital_gain
+        let held_seconds = other.unixtime.saturating_sub(self.unixtime);
+        Disposal {
+            buy_unixtime: self.unixtime,
+            sell_unixtime: other.unixtime,
+            quantity: delta,
+            cost_base_aud: buy_in_aud,
+            proceeds_aud: sell_in_aud,
+            gain: capital_gain,
+            // The ATO rule is "more than 12 months", excluding the acquisition day
+            // itself, so holding for exactly 365 days does not qualify.
+            discount_eligible: held_seconds > DISCOUNT_HOLDING_PERIOD_SECONDS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buy(amount: Decimal, rate: Decimal, fee_aud: Decimal, unixtime: u64) -> Transaction {
+        Transaction::new(
+            "buy".to_string(),
+            amount,
+            Currency("BTC".to_string()),
+            rate,
+            fee_aud,
+            TransactionType::Buy,
+            unixtime,
+        )
+    }
+
+    fn sell(amount: Decimal, rate: Decimal, fee_aud: Decimal, unixtime: u64) -> Transaction {
+        Transaction::new(
+            "sell".to_string(),
+            amount,
+            Currency("BTC".to_string()),
+            rate,
+            fee_aud,
+            TransactionType::Sell,
+            unixtime,
+        )
+    }
+
+    // `Decimal` arithmetic is exact, so a buy/sell pair chosen to not be exact in
+    // binary floating point (a rate of 30000.01) should still net out to a precise gain
+    // rather than one off by a rounding epsilon.
+    #[test]
+    fn subtract_sell_is_exact_for_a_full_match() {
+        let mut buy = buy(dec!(10), dec!(30000), dec!(0), 0);
+        let mut sell = sell(dec!(10), dec!(30000.01), dec!(0), 1);
+        let disposal = buy.subtract_sell(&mut sell);
+        assert_eq!(disposal.quantity, dec!(10));
+        assert_eq!(disposal.cost_base_aud, dec!(300000));
+        assert_eq!(disposal.proceeds_aud, dec!(300000.1));
+        assert_eq!(disposal.gain, dec!(0.1));
+        assert!(buy.is_exhausted());
+        assert!(sell.is_exhausted());
+    }
+
+    #[test]
+    fn subtract_sell_leaves_the_unmatched_remainder_of_a_bigger_buy() {
+        let mut buy = buy(dec!(10), dec!(100), dec!(0), 0);
+        let mut sell = sell(dec!(4), dec!(150), dec!(0), 1);
+        let disposal = buy.subtract_sell(&mut sell);
+        assert_eq!(disposal.quantity, dec!(4));
+        assert_eq!(disposal.gain, dec!(200));
+        assert!(!buy.is_exhausted());
+        assert_eq!(buy.amount, dec!(6));
+        assert!(sell.is_exhausted());
+    }
+
+    #[test]
+    fn is_exhausted_compares_against_an_exact_zero() {
+        let mut buy = buy(dec!(5), dec!(100), dec!(0), 0);
+        let mut sell = sell(dec!(5), dec!(100), dec!(0), 1);
+        buy.subtract_sell(&mut sell);
+        assert!(buy.is_exhausted());
+    }
+
+    // Fees are charged on a transaction's full original amount, so when it's
+    // split across multiple matches, each match should only bear its proportional
+    // share rather than the whole fee.
+    #[test]
+    fn subtract_sell_apportions_buy_and_sell_fees_by_matched_proportion() {
+        let mut buy = buy(dec!(10), dec!(100), dec!(10), 0);
+        let mut first_sell = sell(dec!(4), dec!(200), dec!(8), 1);
+        let first_disposal = buy.subtract_sell(&mut first_sell);
+        // 4/10 of the buy's $10 fee, and all of the fully-matched sell's $8 fee.
+        assert_eq!(first_disposal.cost_base_aud, dec!(404));
+        assert_eq!(first_disposal.proceeds_aud, dec!(792));
+
+        let mut second_sell = sell(dec!(6), dec!(200), dec!(0), 2);
+        let second_disposal = buy.subtract_sell(&mut second_sell);
+        // The remaining 6/10 of the buy's fee.
+        assert_eq!(second_disposal.cost_base_aud, dec!(606));
+        assert!(buy.is_exhausted());
+    }
+
+    #[test]
+    fn financial_year_containing_straddles_30_june_midnight() {
+        // 2023-06-30T23:59:59Z falls in FY2022-23.
+        assert_eq!(FinancialYear::containing(1688169599), FinancialYear(2023));
+        // 2023-07-01T00:00:00Z, one second later, falls in FY2023-24.
+        assert_eq!(FinancialYear::containing(1688169600), FinancialYear(2024));
+    }
+
+    // The ATO discount requires holding an asset for *more than* 12 months, so
+    // held exactly 365 days should just miss out while 365 days plus a second
+    // should just qualify.
+    #[test]
+    fn discount_eligible_is_false_at_exactly_365_days_and_true_one_second_later() {
+        const SECONDS_IN_365_DAYS: u64 = 365 * 24 * 60 * 60;
+
+        let mut buy = buy(dec!(1), dec!(100), dec!(0), 0);
+        let mut sell = sell(dec!(1), dec!(100), dec!(0), SECONDS_IN_365_DAYS);
+        let disposal = buy.subtract_sell(&mut sell);
+        assert!(!disposal.discount_eligible);
+
+        let mut buy = self::buy(dec!(1), dec!(100), dec!(0), 0);
+        let mut sell = self::sell(dec!(1), dec!(100), dec!(0), SECONDS_IN_365_DAYS + 1);
+        let disposal = buy.subtract_sell(&mut sell);
+        assert!(disposal.discount_eligible);
     }
 }